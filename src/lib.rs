@@ -17,6 +17,13 @@
 //! [verify]: trait.Verify.html#tymethod.verify
 //! [CommandStatusError]: enum.CommandStatusError.html
 //! [CommandError]: struct.CommandError.html
+//! [Stringent]: trait.Stringent.html
+//! [output_checked]: trait.Stringent.html#tymethod.output_checked
+//! [ContextError]: struct.ContextError.html
+//! [DecodeOutput]: trait.DecodeOutput.html
+//! [ExitStatusExt]: https://doc.rust-lang.org/std/os/unix/process/trait.ExitStatusExt.html
+//! [WaitTimeout]: trait.WaitTimeout.html
+//! [wait_timeout_verify]: trait.WaitTimeout.html#tymethod.wait_timeout_verify
 //!
 //! # Examples
 //!
@@ -138,11 +145,18 @@
 //!         Some(code) => Err(CommandStatusError::ExitCode(code)),
 //!         None => {
 //!             #[cfg(unix)]
-//!                 use std::os::unix::process::ExitStatusExt;
-//!                 let signal = status.signal();
+//!             use std::os::unix::process::ExitStatusExt;
+//!             #[cfg(unix)]
+//!             let info = stringent::SignalInfo {
+//!                 term_signal: status.signal(),
+//!                 core_dumped: status.core_dumped(),
+//!             };
 //!             #[cfg(not(unix))]
-//!                 let signal = None;
-//!             Err(CommandStatusError::Signal(signal))
+//!             let info = stringent::SignalInfo {
+//!                 term_signal: None,
+//!                 core_dumped: false,
+//!             };
+//!             Err(CommandStatusError::Signal(info))
 //!         }
 //!     }
 //! }
@@ -161,6 +175,55 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Beyond `verify()`
+//!
+//! A few commands signal meaning through exit codes other than `0`, e.g. `grep` returning
+//! `1` for "no match". `VerifyWith` adds `verify_codes()` and `verify_if()`, which work like
+//! `verify()` but also accept the given codes (or any code matching a predicate) as success:
+//!
+//! ```no_run
+//! use std::process::Command;
+//! use stringent::{CommandStatusError, VerifyWith};
+//!
+//! fn grep(pattern: &str, file: &str) -> Result<bool, CommandStatusError> {
+//!     let status = Command::new("grep").args(&[pattern, file]).status().verify_codes(&[0, 1])?;
+//!     Ok(status.success())
+//! }
+//! ```
+//!
+//! `Stringent` adds `status_checked()`, `output_checked()`, and `spawn_checked()` to
+//! [`Command`][Command] itself; they behave like the `verify()` methods above, but attach the
+//! formatted command line and arguments to the resulting error, which makes failures in a
+//! pipeline of several commands easier to tell apart:
+//!
+//! ```no_run
+//! use std::process::Command;
+//! use stringent::{ContextError, Stringent};
+//!
+//! fn run_commands() -> Result<(), ContextError> {
+//!     Command::new("first").status_checked()?;
+//!     Command::new("second").status_checked()?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! `DecodeOutput` adds `stdout_utf8()` and `map_stdout()`, which decode or parse a verified
+//! command's captured `stdout`, turning invalid UTF-8 or a failed parse into a
+//! `CommandStatusError` instead of panicking:
+//!
+//! ```no_run
+//! use std::process::Command;
+//! use stringent::{CommandError, DecodeOutput, Verify};
+//!
+//! fn run_command() -> Result<String, CommandError> {
+//!     Command::new("cmd").output().verify().stdout_utf8()
+//! }
+//! ```
+//!
+//! Finally, `WaitTimeout` adds `wait_timeout_verify()` to [`Child`][Child], for children that
+//! might hang; it verifies the exit status as usual, but kills the child and returns
+//! `CommandStatusError::TimedOut` if it hasn't exited within the given duration.
 
 #![deny(warnings, unused, clippy::all, clippy::pedantic)]
 #![deny(missing_copy_implementations, missing_debug_implementations)]
@@ -170,8 +233,10 @@
 use std::error::Error;
 use std::fmt;
 use std::io;
-use std::process::{Child, ExitStatus, Output};
+use std::process::{Child, Command, ExitStatus, Output};
 use std::result::Result;
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Adds error cases for commands that exit with error codes or that are killed
 #[derive(Debug)]
@@ -184,8 +249,16 @@ pub enum CommandStatusError {
     SpawnFailed(io::Error),
     /// Holds the exit code when a command terminates with an error
     ExitCode(i32),
-    /// Holds the signal number when the command is killed by a signal (only on unix)
-    Signal(Option<i32>),
+    /// Holds details about the signal that terminated the command (only available on unix);
+    /// a stopped or continued state is not recorded
+    Signal(SignalInfo),
+    /// Holds a description of the failure when the command succeeds but its captured
+    /// `stdout` can't be decoded or parsed, e.g. by `stdout_utf8()` or `map_stdout()` on
+    /// [`DecodeOutput`][DecodeOutput]
+    OutputDecode(String),
+    /// Holds the timeout that elapsed when [`wait_timeout_verify`][wait_timeout_verify] gave up
+    /// waiting for a child; the child is killed before this error is returned
+    TimedOut(Duration),
 }
 
 impl fmt::Display for CommandStatusError {
@@ -194,10 +267,15 @@ impl fmt::Display for CommandStatusError {
         match self {
             SpawnFailed(io) => write!(f, "Spawn failed: {}", io),
             ExitCode(code) => write!(f, "Exit code {}", code),
-            Signal(signal) => match signal {
+            Signal(info) => match info.term_signal {
+                Some(sig) if info.core_dumped => {
+                    write!(f, "Terminated by signal {} (core dumped)", sig)
+                }
                 Some(sig) => write!(f, "Terminated by signal {}", sig),
                 None => write!(f, "Terminated"),
             },
+            OutputDecode(message) => write!(f, "Failed to decode output: {}", message),
+            TimedOut(dur) => write!(f, "Timed out after {:?}", dur),
         }
     }
 }
@@ -234,11 +312,17 @@ pub struct CommandError {
     pub err: CommandStatusError,
     /// Saved `stdout` from the command.
     pub output: Option<Std>,
+    /// The program and arguments that produced this error, formatted with `{:?}`. Only set
+    /// when this `CommandError` came from [`output_checked`][output_checked].
+    pub command: Option<String>,
 }
 
 impl fmt::Display for CommandError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.err.fmt(f)
+        match &self.command {
+            Some(command) => write!(f, "`{}`: {}", command, self.err),
+            None => self.err.fmt(f),
+        }
     }
 }
 
@@ -258,18 +342,53 @@ pub trait Verify<T, E> {
     fn verify(self) -> Result<T, E>;
 }
 
+/// Adds `verify_codes()` and `verify_if()` methods to the same `io::Result` values that
+/// `verify()` handles, for callers whose commands signal meaning through particular
+/// non-zero exit codes (e.g. `grep` returning `1` for "no match").
+pub trait VerifyWith<T, E> {
+    /// Like `verify()`, but also treats any exit code in `accepted` as success rather than
+    /// as an `ExitCode` on [`CommandStatusError`][CommandStatusError].
+    fn verify_codes(self, accepted: &[i32]) -> Result<T, E>;
+
+    /// Like `verify()`, but treats any exit code for which `pred` returns `true` as
+    /// success.
+    fn verify_if<P: Fn(i32) -> bool>(self, pred: P) -> Result<T, E>;
+}
+
+/// Holds the signal-related details that [`ExitStatusExt`][ExitStatusExt] exposes on unix, so
+/// a `Signal` on [`CommandStatusError`][CommandStatusError] can distinguish a crash that
+/// dumped core from an ordinary kill. Off unix, both fields are always `None`/`false`.
+///
+/// `ExitStatusExt` also exposes `stopped_signal()` and `continued()`, but `Child::wait` and
+/// `Child::try_wait` call `waitpid` without `WUNTRACED`/`WCONTINUED`, so a status produced by
+/// this crate's own API is never actually stopped or continued; those two are left off until
+/// something in this crate can request that kind of notification.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SignalInfo {
+    /// The signal that terminated the process, if any.
+    pub term_signal: Option<i32>,
+    /// Whether the process produced a core dump when it was terminated.
+    pub core_dumped: bool,
+}
+
 #[cfg(unix)]
-fn signal_of(status: ExitStatus) -> Option<i32> {
+fn signal_of(status: ExitStatus) -> SignalInfo {
     use std::os::unix::process::ExitStatusExt;
-    status.signal()
+    SignalInfo {
+        term_signal: status.signal(),
+        core_dumped: status.core_dumped(),
+    }
 }
 
 // I don't think this can ever get called — it would mean that `code()`
 // on Windows returned None, which as far as I know isn't possible.
 // But I don't know very far!
 #[cfg(not(unix))]
-fn signal_of(status: ExitStatus) -> Option<i32> {
-    None
+fn signal_of(_status: ExitStatus) -> SignalInfo {
+    SignalInfo {
+        term_signal: None,
+        core_dumped: false,
+    }
 }
 
 trait StringentResult
@@ -278,11 +397,18 @@ where
 {
     fn option_status(self) -> Option<ExitStatus>;
     fn stringent_result(self) -> Result<Self, CommandStatusError> {
+        self.stringent_result_with(|_| false)
+    }
+    fn stringent_result_with<P>(self, accept: P) -> Result<Self, CommandStatusError>
+    where
+        P: Fn(i32) -> bool,
+    {
         use CommandStatusError::*;
         match self.option_status() {
             None => Ok(self),
             Some(status) if status.success() => Ok(self),
             Some(status) => match status.code() {
+                Some(code) if accept(code) => Ok(self),
                 Some(code) => Err(ExitCode(code)),
                 None => Err(Signal(signal_of(status))),
             },
@@ -320,6 +446,35 @@ impl Verify<Option<ExitStatus>, CommandStatusError> for Result<Option<ExitStatus
     }
 }
 
+impl VerifyWith<ExitStatus, CommandStatusError> for Result<ExitStatus, io::Error> {
+    fn verify_codes(self, accepted: &[i32]) -> Result<ExitStatus, CommandStatusError> {
+        self.verify_if(|code| accepted.contains(&code))
+    }
+
+    fn verify_if<P: Fn(i32) -> bool>(self, pred: P) -> Result<ExitStatus, CommandStatusError> {
+        match self {
+            Err(io_err) => Err(CommandStatusError::SpawnFailed(io_err)),
+            Ok(status) => status.stringent_result_with(pred),
+        }
+    }
+}
+
+impl VerifyWith<Option<ExitStatus>, CommandStatusError> for Result<Option<ExitStatus>, io::Error> {
+    fn verify_codes(self, accepted: &[i32]) -> Result<Option<ExitStatus>, CommandStatusError> {
+        self.verify_if(|code| accepted.contains(&code))
+    }
+
+    fn verify_if<P: Fn(i32) -> bool>(
+        self,
+        pred: P,
+    ) -> Result<Option<ExitStatus>, CommandStatusError> {
+        match self {
+            Err(io_err) => Err(CommandStatusError::SpawnFailed(io_err)),
+            Ok(status) => status.stringent_result_with(pred),
+        }
+    }
+}
+
 impl Verify<Child, CommandStatusError> for Result<Child, io::Error> {
     fn verify(self) -> Result<Child, CommandStatusError> {
         match self {
@@ -335,6 +490,7 @@ impl Verify<Output, CommandError> for Result<Output, io::Error> {
             Err(io_err) => Err(CommandError {
                 err: CommandStatusError::SpawnFailed(io_err),
                 output: None,
+                command: None,
             }),
             Ok(output) => match output.status.stringent_result() {
                 Err(err) => Err(CommandError {
@@ -343,6 +499,34 @@ impl Verify<Output, CommandError> for Result<Output, io::Error> {
                         stdout: output.stdout,
                         stderr: output.stderr,
                     }),
+                    command: None,
+                }),
+                Ok(_) => Ok(output),
+            },
+        }
+    }
+}
+
+impl VerifyWith<Output, CommandError> for Result<Output, io::Error> {
+    fn verify_codes(self, accepted: &[i32]) -> Result<Output, CommandError> {
+        self.verify_if(|code| accepted.contains(&code))
+    }
+
+    fn verify_if<P: Fn(i32) -> bool>(self, pred: P) -> Result<Output, CommandError> {
+        match self {
+            Err(io_err) => Err(CommandError {
+                err: CommandStatusError::SpawnFailed(io_err),
+                output: None,
+                command: None,
+            }),
+            Ok(output) => match output.status.stringent_result_with(pred) {
+                Err(err) => Err(CommandError {
+                    err,
+                    output: Some(Std {
+                        stdout: output.stdout,
+                        stderr: output.stderr,
+                    }),
+                    command: None,
                 }),
                 Ok(_) => Ok(output),
             },
@@ -355,6 +539,155 @@ impl From<CommandStatusError> for CommandError {
         Self {
             err: status_error,
             output: None,
+            command: None,
+        }
+    }
+}
+
+/// Pairs a [`CommandStatusError`][CommandStatusError] with the formatted command line that
+/// produced it, as returned by `status_checked()` and `spawn_checked()` on
+/// [`Stringent`][Stringent].
+#[derive(Debug)]
+pub struct ContextError {
+    /// The underlying status error.
+    pub err: CommandStatusError,
+    /// The program and arguments that produced `err`, formatted with `{:?}`.
+    pub command: Option<String>,
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.command {
+            Some(command) => write!(f, "`{}`: {}", command, self.err),
+            None => self.err.fmt(f),
+        }
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.err.source()
+    }
+}
+
+impl From<CommandStatusError> for ContextError {
+    fn from(err: CommandStatusError) -> Self {
+        Self { err, command: None }
+    }
+}
+
+/// Adds `status_checked()`, `output_checked()`, and `spawn_checked()` methods to
+/// [`Command`][Command] that behave like `status().verify()`, `output().verify()`, and
+/// `spawn().verify()`, but attach the formatted command line and arguments to the resulting
+/// error, so failures in a pipeline of several commands are easier to diagnose.
+pub trait Stringent {
+    /// Runs the command to completion, verifying the exit status and attaching the command
+    /// line to any resulting error.
+    fn status_checked(&mut self) -> Result<ExitStatus, ContextError>;
+
+    /// Runs the command to completion, capturing `stdout`/`stderr`, verifying the exit
+    /// status, and attaching the command line to any resulting error.
+    fn output_checked(&mut self) -> Result<Output, CommandError>;
+
+    /// Spawns the command, attaching the command line to an error if spawning fails.
+    fn spawn_checked(&mut self) -> Result<Child, ContextError>;
+}
+
+impl Stringent for Command {
+    fn status_checked(&mut self) -> Result<ExitStatus, ContextError> {
+        self.status().verify().map_err(|err| ContextError {
+            err,
+            command: Some(format!("{:?}", self)),
+        })
+    }
+
+    fn output_checked(&mut self) -> Result<Output, CommandError> {
+        self.output().verify().map_err(|err| CommandError {
+            command: Some(format!("{:?}", self)),
+            ..err
+        })
+    }
+
+    fn spawn_checked(&mut self) -> Result<Child, ContextError> {
+        self.spawn().verify().map_err(|err| ContextError {
+            err,
+            command: Some(format!("{:?}", self)),
+        })
+    }
+}
+
+/// Adds combinators to the `Result<Output, CommandError>` returned by `output().verify()`
+/// (or `output_checked()` on [`Stringent`][Stringent]) that decode or parse the captured
+/// `stdout`, after the command's exit status has already been verified.
+pub trait DecodeOutput {
+    /// Decodes the captured `stdout` as UTF-8, turning invalid UTF-8 into an `OutputDecode`
+    /// on [`CommandStatusError`][CommandStatusError] instead of silently losing data or
+    /// panicking.
+    fn stdout_utf8(self) -> Result<String, CommandError>;
+
+    /// Applies `f` to the captured `stdout`, turning an `Err` returned by `f` into an
+    /// `OutputDecode` on [`CommandStatusError`][CommandStatusError].
+    fn map_stdout<T, Err, F>(self, f: F) -> Result<T, CommandError>
+    where
+        F: FnOnce(&[u8]) -> Result<T, Err>,
+        Err: fmt::Display;
+}
+
+impl DecodeOutput for Result<Output, CommandError> {
+    fn stdout_utf8(self) -> Result<String, CommandError> {
+        self.map_stdout(|bytes| std::str::from_utf8(bytes).map(str::to_owned))
+    }
+
+    fn map_stdout<T, Err, F>(self, f: F) -> Result<T, CommandError>
+    where
+        F: FnOnce(&[u8]) -> Result<T, Err>,
+        Err: fmt::Display,
+    {
+        let output = self?;
+        f(&output.stdout).map_err(|err| CommandError {
+            err: CommandStatusError::OutputDecode(err.to_string()),
+            output: Some(Std {
+                stdout: output.stdout,
+                stderr: output.stderr,
+            }),
+            command: None,
+        })
+    }
+}
+
+/// Adds a timeout-aware wait to a spawned [`Child`][Child], for children that might hang.
+pub trait WaitTimeout {
+    /// Waits for the child to exit within `dur`, verifying its exit status. If the child is
+    /// still running once `dur` elapses, it is killed and a `TimedOut` on
+    /// [`CommandStatusError`][CommandStatusError] is returned.
+    fn wait_timeout_verify(&mut self, dur: Duration) -> Result<ExitStatus, CommandStatusError>;
+}
+
+impl WaitTimeout for Child {
+    fn wait_timeout_verify(&mut self, dur: Duration) -> Result<ExitStatus, CommandStatusError> {
+        let deadline = Instant::now().checked_add(dur).unwrap_or_else(|| {
+            Instant::now() + Duration::from_secs(u64::from(u32::MAX))
+        });
+        let mut backoff = Duration::from_millis(1);
+        loop {
+            match self.try_wait() {
+                Err(io_err) => {
+                    let _ = self.kill();
+                    let _ = self.wait();
+                    return Err(CommandStatusError::SpawnFailed(io_err));
+                }
+                Ok(Some(status)) => return status.stringent_result(),
+                Ok(None) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        let _ = self.kill();
+                        let _ = self.wait();
+                        return Err(CommandStatusError::TimedOut(dur));
+                    }
+                    thread::sleep(backoff.min(deadline - now));
+                    backoff = (backoff * 2).min(Duration::from_millis(100));
+                }
+            }
         }
     }
 }
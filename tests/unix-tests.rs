@@ -4,7 +4,7 @@
 use std::process::ExitStatus;
 use std::os::unix::process::ExitStatusExt;
 
-use stringent::{CommandError, Stringent};
+use stringent::{CommandStatusError, Verify};
 
 const XCPU: i32 = 24;
 const PANIC: i32 = 101;
@@ -61,7 +61,7 @@ fn test_panic() {
     if let Some(status) = panicked() {
         let result = Ok(status).verify();
         match result {
-            Err(CommandError::ExitCode(PANIC)) => {}
+            Err(CommandStatusError::ExitCode(PANIC)) => {}
             _ => panic!("Expected panic status to report a bad exit code"),
         }
     }
@@ -72,7 +72,7 @@ fn test_killed_by_signal() {
     if let Some(status) = killed_by_signal() {
         let result = Ok(status).verify();
         match result {
-            Err(CommandError::Signal(Some(XCPU))) => {}
+            Err(CommandStatusError::Signal(info)) if info.term_signal == Some(XCPU) => {}
             _ => panic!("Expected killed_by_signal to report XCPU"),
         }
     }
@@ -97,7 +97,7 @@ use std::path::Path;
     if ! Path::new(cmd).is_file() {
         match Command::new(cmd).status().verify() {
             Ok(_) => panic!("{} should not have succeeded", cmd),
-            Err(CommandError::SpawnFailed(_)) => {},
+            Err(CommandStatusError::SpawnFailed(_)) => {},
             Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
         }
     }
@@ -108,7 +108,50 @@ use std::path::Path;
     if Path::new(cmd).is_file() {
         match Command::new(cmd).stderr(Stdio::null()).status().verify() {
             Ok(_) => panic!("{} should not have succeeded", cmd),
-            Err(CommandError::ExitCode(_)) => {}, // Sleep with no arguments should complain
+            Err(CommandStatusError::ExitCode(_)) => {}, // Sleep with no arguments should complain
+            Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
+        }
+    }
+}
+
+#[test] fn verify_codes_accepts_a_listed_exit_code() {
+    use stringent::VerifyWith;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        // With no arguments, sleep exits 1.
+        match Command::new(cmd).stderr(Stdio::null()).status().verify_codes(&[0, 1]) {
+            Ok(_) => {},
+            Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
+        }
+    }
+}
+
+#[test] fn verify_codes_still_rejects_an_unlisted_exit_code() {
+    use stringent::VerifyWith;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        // With no arguments, sleep exits 1, which isn't in the accepted set.
+        match Command::new(cmd).stderr(Stdio::null()).status().verify_codes(&[0, 2]) {
+            Ok(_) => panic!("{} should not have succeeded", cmd),
+            Err(CommandStatusError::ExitCode(1)) => {},
+            Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
+        }
+    }
+}
+
+#[test] fn verify_if_accepts_codes_matching_the_predicate() {
+    use stringent::VerifyWith;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        match Command::new(cmd)
+            .stderr(Stdio::null())
+            .status()
+            .verify_if(|code| code == 1)
+        {
+            Ok(_) => {},
             Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
         }
     }
@@ -120,8 +163,9 @@ use std::path::Path;
         match Command::new(cmd).output().verify() {
             Ok(_) => panic!("{} should not have succeeded", cmd),
             Err(output) => match output.err {
-                CommandError::ExitCode(_) => {
-                    if output.stderr.len() == 0 {
+                CommandStatusError::ExitCode(_) => {
+                    let stderr = &output.output.as_ref().expect("output should be captured").stderr;
+                    if stderr.is_empty() {
                         panic!("Expected to capture {}'s stderr", cmd);
                     }
                 },
@@ -145,7 +189,7 @@ use std::path::Path;
         };
         match result {
             Ok(_) => panic!("Killed command {} should not have succeeded", cmd),
-            Err(CommandError::Signal(_)) => {},
+            Err(CommandStatusError::Signal(_)) => {},
             Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
         }
     }
@@ -159,9 +203,164 @@ use std::path::Path;
         match child.wait_with_output().verify() {
             Ok(_) => panic!("Killed command {} should not have succeeded", cmd),
             Err(output) => match output.err {
-                CommandError::Signal(_) => { },
+                CommandStatusError::Signal(_) => { },
                 _ => panic!("Unexpected error ({}) in executing {}", output.err, cmd),
             }
         };
     }
 }
+
+#[test] fn stdout_utf8_decodes_valid_output() {
+    use stringent::DecodeOutput;
+
+    let cmd = "/bin/echo";
+    if Path::new(cmd).is_file() {
+        match Command::new(cmd).arg("hello").output().verify().stdout_utf8() {
+            Ok(stdout) => assert_eq!(stdout, "hello\n"),
+            Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
+        }
+    }
+}
+
+#[test] fn stdout_utf8_reports_invalid_utf8() {
+    use stringent::DecodeOutput;
+
+    let cmd = "/bin/sh";
+    if Path::new(cmd).is_file() {
+        match Command::new(cmd)
+            .args(&["-c", "printf '\\377'"])
+            .output()
+            .verify()
+            .stdout_utf8()
+        {
+            Ok(stdout) => panic!("Expected invalid UTF-8 to be rejected, got {:?}", stdout),
+            Err(output) => match output.err {
+                CommandStatusError::OutputDecode(_) => {},
+                _ => panic!("Unexpected error ({}) in executing {}", output.err, cmd),
+            }
+        }
+    }
+}
+
+#[test] fn map_stdout_applies_the_closure_on_success() {
+    use stringent::DecodeOutput;
+
+    let cmd = "/bin/echo";
+    if Path::new(cmd).is_file() {
+        let result = Command::new(cmd)
+            .arg("hello")
+            .output()
+            .verify()
+            .map_stdout(|bytes| Ok::<_, std::convert::Infallible>(bytes.len()));
+        match result {
+            Ok(len) => assert_eq!(len, "hello\n".len()),
+            Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
+        }
+    }
+}
+
+#[test] fn map_stdout_reports_the_closures_error() {
+    use stringent::DecodeOutput;
+
+    let cmd = "/bin/echo";
+    if Path::new(cmd).is_file() {
+        let result = Command::new(cmd)
+            .arg("hello")
+            .output()
+            .verify()
+            .map_stdout(|_| Err::<(), _>("not today"));
+        match result {
+            Ok(_) => panic!("Expected map_stdout's closure error to be reported"),
+            Err(output) => match output.err {
+                CommandStatusError::OutputDecode(ref msg) if msg.contains("not today") => {},
+                _ => panic!("Unexpected error ({}) in executing {}", output.err, cmd),
+            }
+        }
+    }
+}
+
+#[test] fn status_checked_attaches_the_command_line_on_failure() {
+    use stringent::Stringent;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        match Command::new(cmd).stderr(Stdio::null()).status_checked() {
+            Ok(_) => panic!("{} should not have succeeded", cmd),
+            Err(context) => {
+                let command = context.command.as_deref().expect("command line should be set");
+                if !command.contains(cmd) {
+                    panic!("Expected the command line ({}) to mention {}", command, cmd);
+                }
+                if !context.to_string().contains(cmd) {
+                    panic!("Expected Display ({}) to mention {}", context, cmd);
+                }
+            }
+        }
+    }
+}
+
+#[test] fn spawn_checked_attaches_the_command_line_on_failure() {
+    use stringent::Stringent;
+
+    let cmd = "/nonexistent_command";
+    if !Path::new(cmd).is_file() {
+        match Command::new(cmd).spawn_checked() {
+            Ok(_) => panic!("{} should not have succeeded", cmd),
+            Err(context) => {
+                let command = context.command.as_deref().expect("command line should be set");
+                if !command.contains(cmd) {
+                    panic!("Expected the command line ({}) to mention {}", command, cmd);
+                }
+            }
+        }
+    }
+}
+
+#[test] fn output_checked_attaches_the_command_line_on_failure() {
+    use stringent::Stringent;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        match Command::new(cmd).output_checked() {
+            Ok(_) => panic!("{} should not have succeeded", cmd),
+            Err(error) => {
+                let command = error.command.as_deref().expect("command line should be set");
+                if !command.contains(cmd) {
+                    panic!("Expected the command line ({}) to mention {}", command, cmd);
+                }
+            }
+        }
+    }
+}
+
+#[test] fn wait_timeout_verify_times_out_and_kills_the_child() {
+    use std::time::Duration;
+    use stringent::WaitTimeout;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        let mut child = Command::new(cmd).arg("3").spawn().verify().expect(cmd);
+        match child.wait_timeout_verify(Duration::from_millis(50)) {
+            Err(CommandStatusError::TimedOut(_)) => {},
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+        match child.try_wait() {
+            Ok(Some(_)) => {},
+            other => panic!("Expected the timed-out child to have been killed, got {:?}", other),
+        }
+    }
+}
+
+#[test] fn wait_timeout_verify_reports_success_before_the_deadline() {
+    use std::time::Duration;
+    use stringent::WaitTimeout;
+
+    let cmd = "/bin/sleep";
+    if Path::new(cmd).is_file() {
+        let mut child = Command::new(cmd).arg("0").spawn().verify().expect(cmd);
+        match child.wait_timeout_verify(Duration::from_secs(5)) {
+            Ok(_) => {},
+            Err(e) => panic!("Unexpected error ({}) in executing {}", e, cmd),
+        }
+    }
+}